@@ -0,0 +1,129 @@
+//! Self-hosted standalone runner for `Whisper`.
+//!
+//! Unlike `plugin_main!`, which packages the crate as a VST2 DLL for a DAW
+//! to load, this binary drives the same `Whisper::process`/`process_events`
+//! and GUI directly: audio goes out through cpal, MIDI comes in from an
+//! optional `midir` input port, and the editor opens its own top-level
+//! window rather than embedding into a host `HWND`. This lets contributors
+//! test the synth and GUI end-to-end with `cargo run`.
+use std::sync::{Arc, Mutex};
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use vst::host::HostBuffer;
+use vst::plugin::Plugin;
+
+use vst_rs_example_iced::{run_standalone_gui, Whisper};
+
+/// Frames we size the scratch de-interleave buffers for up front, so the
+/// realtime cpal callback doesn't allocate on every block. Most hosts ask
+/// for far fewer frames per callback than this; if one ever asks for more,
+/// the buffers grow once rather than panicking.
+const EXPECTED_MAX_BLOCK_FRAMES: usize = 4096;
+
+fn main() {
+    simple_logging::log_to_file("standalone.log", log::LevelFilter::Trace).ok();
+
+    // `voices` and `params` live behind this `Arc<Mutex<..>>` so the cpal
+    // audio callback, the midir MIDI callback, and the GUI thread can all
+    // reach the same `Whisper` instance.
+    let plugin = Arc::new(Mutex::new(Whisper::default()));
+
+    let host = cpal::default_host();
+    let device = host
+        .default_output_device()
+        .expect("no default output device");
+
+    // `build_output_stream` below is built for an `f32` callback, so the
+    // config we pick has to actually be f32. The device's default isn't
+    // guaranteed to be (e.g. some ALSA devices default to i16) — fall back
+    // to searching its supported configs for an f32 one instead of
+    // assuming.
+    let default_config = device
+        .default_output_config()
+        .expect("no default output config");
+    let supported_config = if default_config.sample_format() == cpal::SampleFormat::F32 {
+        default_config
+    } else {
+        device
+            .supported_output_configs()
+            .expect("no supported output configs")
+            .find(|c| c.sample_format() == cpal::SampleFormat::F32)
+            .expect("no f32-capable output config")
+            .with_max_sample_rate()
+    };
+    let config = supported_config.config();
+    let channels = config.channels as usize;
+
+    {
+        let mut p = plugin.lock().unwrap();
+        p.set_sample_rate(config.sample_rate.0 as f32);
+    }
+
+    let stream_plugin = Arc::clone(&plugin);
+    // `HostBuffer` is the host-side counterpart to the plugin-side
+    // `AudioBuffer`: it owns no samples itself, just binds borrowed slices
+    // into the shape `Plugin::process` expects.
+    let mut host_buffer: HostBuffer<f32> = HostBuffer::new(0, 2);
+    let mut left = vec![0f32; EXPECTED_MAX_BLOCK_FRAMES];
+    let mut right = vec![0f32; EXPECTED_MAX_BLOCK_FRAMES];
+    let stream = device
+        .build_output_stream(
+            &config,
+            move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
+                let frames = data.len() / channels;
+                if frames > left.len() {
+                    left.resize(frames, 0.0);
+                    right.resize(frames, 0.0);
+                }
+
+                {
+                    let mut p = stream_plugin.lock().unwrap();
+                    // No audio input ports, so the input side is empty.
+                    let mut audio_buffer =
+                        host_buffer.bind(&[], &mut [&mut left[..frames], &mut right[..frames]]);
+                    p.process(&mut audio_buffer);
+                }
+
+                for (frame, out) in data.chunks_mut(channels).enumerate() {
+                    for (ch, sample) in out.iter_mut().enumerate() {
+                        *sample = if ch % 2 == 0 { left[frame] } else { right[frame] };
+                    }
+                }
+            },
+            |err| log::error!("cpal output stream error: {}", err),
+        )
+        .expect("failed to build cpal output stream");
+    stream.play().expect("failed to start cpal output stream");
+
+    // MIDI input is optional: keep running even if there's no port/device.
+    let _midi_connection = open_midi_input(Arc::clone(&plugin));
+
+    let params = plugin.lock().unwrap().params_handle();
+    run_standalone_gui(params);
+}
+
+/// Opens the first available `midir` input port, if any, and forwards
+/// every incoming message into `Whisper`'s note tracking through the same
+/// path `process_events` uses for host MIDI.
+fn open_midi_input(plugin: Arc<Mutex<Whisper>>) -> Option<midir::MidiInputConnection<()>> {
+    let midi_in = midir::MidiInput::new("whisper-standalone").ok()?;
+    let port = midi_in.ports().into_iter().next()?;
+    let port_name = midi_in.port_name(&port).unwrap_or_default();
+
+    midi_in
+        .connect(
+            &port,
+            "whisper-standalone-input",
+            move |_timestamp, message, _| {
+                if message.len() < 3 {
+                    return;
+                }
+                let mut data = [0u8; 3];
+                data.copy_from_slice(&message[..3]);
+                plugin.lock().unwrap().handle_midi_message(data);
+            },
+            (),
+        )
+        .map_err(|err| log::error!("failed to connect to MIDI port {}: {}", port_name, err))
+        .ok()
+}
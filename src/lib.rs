@@ -1,39 +1,268 @@
-#![feature(generators, generator_trait)]
 #[macro_use]
 extern crate vst;
 
 use log::LevelFilter;
-use rand::random;
 use std::os::raw::c_void;
-use std::sync::Arc;
-use vst::api::{Events, Supported};
+use std::sync::{Arc, Mutex};
+use vst::api::{Events, EventType, MidiEvent, MidiEventFlags, Supported};
 use vst::buffer::AudioBuffer;
 use vst::editor::Editor;
 use vst::event::Event;
-use vst::plugin::{CanDo, Category, Info, Plugin, PluginParameters};
+use vst::host::Host;
+use vst::plugin::{CanDo, Category, HostCallback, Info, Plugin, PluginParameters};
 use vst::util::AtomicFloat;
 
-#[derive(Default)]
-struct Whisper {
+const MAX_OUTGOING_EVENTS: usize = 256;
+
+/// Fixed-capacity outgoing MIDI buffer handed to the host through
+/// `HostCallback::process_events`. Its layout mirrors what `vst::api::Events`
+/// expects: a pointer array the host walks, backed by a parallel array
+/// holding the actual `MidiEvent` values the pointers point into, so the
+/// pointers stay valid for the lifetime of a single `process` call.
+#[repr(C)]
+struct SendEventBuffer {
+    num_events: i32,
+    _reserved: isize,
+    events: [*mut MidiEvent; MAX_OUTGOING_EVENTS],
+    event_storage: [MidiEvent; MAX_OUTGOING_EVENTS],
+}
+
+/// A blank but well-formed `MidiEvent`: every field set to a real value
+/// rather than produced with `mem::zeroed`, since `event_type: EventType`
+/// has no variant at discriminant 0 and zeroing it would be UB.
+fn blank_midi_event() -> MidiEvent {
+    MidiEvent {
+        event_type: EventType::Midi,
+        byte_size: std::mem::size_of::<MidiEvent>() as i32,
+        delta_frames: 0,
+        flags: MidiEventFlags::REALTIME_EVENT.bits(),
+        note_length: 0,
+        note_offset: 0,
+        midi_data: [0, 0, 0],
+        _midi_reserved: 0,
+        detune: 0,
+        note_off_velocity: 0,
+        _reserved1: 0,
+        _reserved2: 0,
+    }
+}
+
+impl SendEventBuffer {
+    fn new() -> Self {
+        Self {
+            num_events: 0,
+            _reserved: 0,
+            events: [std::ptr::null_mut(); MAX_OUTGOING_EVENTS],
+            event_storage: std::array::from_fn(|_| blank_midi_event()),
+        }
+    }
+
+    fn push(&mut self, event: MidiEvent) {
+        let idx = self.num_events as usize;
+        if idx >= MAX_OUTGOING_EVENTS {
+            return;
+        }
+        self.event_storage[idx] = event;
+        self.events[idx] = &mut self.event_storage[idx] as *mut MidiEvent;
+        self.num_events += 1;
+    }
+
+    fn clear(&mut self) {
+        self.num_events = 0;
+    }
+
+    fn as_events(&mut self) -> &Events {
+        unsafe { &*(self as *mut SendEventBuffer as *const Events) }
+    }
+}
+
+impl Default for SendEventBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A single held note: a phase accumulator driven at `phase_inc` cycles per
+/// sample, scaled by `gain` from the note-on velocity.
+struct Voice {
+    note: u8,
+    phase: f32,
+    phase_inc: f32,
+    gain: f32,
+}
+
+pub struct Whisper {
     params: Arc<WhisperParameters>,
-    // Added a counter in our plugin struct.
-    notes: u8,
+    voices: Vec<Voice>,
+    sample_rate: f32,
+    host: HostCallback,
+    outgoing: SendEventBuffer,
+    // Scratch mix buffer reused across `process` calls so the realtime
+    // audio thread doesn't allocate every block; it only grows (and then
+    // stays put) if the host ever asks for a larger block size.
+    mix_buffer: Vec<f32>,
+}
+
+impl Default for Whisper {
+    fn default() -> Self {
+        Self {
+            params: Default::default(),
+            voices: Vec::new(),
+            sample_rate: 44_100.0,
+            host: Default::default(),
+            outgoing: Default::default(),
+            mix_buffer: Vec::new(),
+        }
+    }
+}
+
+impl Whisper {
+    /// Clone of the shared parameter block, used by the standalone host to
+    /// hand the same `Arc` to both the audio thread and the GUI thread.
+    pub fn params_handle(&self) -> Arc<WhisperParameters> {
+        self.params.clone()
+    }
+
+    /// Feed a single raw MIDI message (status, data1, data2) into the voice
+    /// list. Shared by `process_events` (host MIDI) and the standalone
+    /// binary's `midir` input callback so both paths agree on what counts
+    /// as a note on/off.
+    pub fn handle_midi_message(&mut self, data: [u8; 3]) {
+        match data[0] {
+            // note on, unless velocity is 0 (which MIDI treats as note off)
+            144 if data[2] > 0 => self.note_on(data[1], data[2]),
+            144 => self.note_off(data[1]),
+
+            // note off
+            128 => self.note_off(data[1]),
+            _ => (),
+        }
+    }
+
+    fn note_on(&mut self, note: u8, velocity: u8) {
+        let freq = 440.0 * 2f32.powf((note as f32 - 69.0) / 12.0);
+        let voice = Voice {
+            note,
+            phase: 0.0,
+            phase_inc: freq / self.sample_rate,
+            gain: velocity as f32 / 127.0,
+        };
+
+        // A repeated note-on for a pitch that's already sounding retriggers
+        // that voice instead of stacking a duplicate, so one note-off is
+        // always enough to silence it again.
+        if let Some(existing) = self.voices.iter_mut().find(|v| v.note == note) {
+            *existing = voice;
+        } else {
+            self.voices.push(voice);
+        }
+
+        // Echo the note back up an octave so outgoing MIDI is testable
+        // without a dedicated arpeggiator yet.
+        self.queue_midi([144, Self::echoed_note(note), velocity], 0);
+    }
+
+    fn note_off(&mut self, note: u8) {
+        if let Some(index) = self.voices.iter().position(|v| v.note == note) {
+            self.voices.remove(index);
+        }
+
+        // Echo the matching note-off, so the echoed note-on above doesn't
+        // leave a dangling voice hanging in whatever's downstream.
+        self.queue_midi([128, Self::echoed_note(note), 0], 0);
+    }
+
+    /// The pitch `note_on`/`note_off` echo a received note at: up an octave,
+    /// clamped so it stays a valid MIDI note number.
+    fn echoed_note(note: u8) -> u8 {
+        note.saturating_add(12).min(127)
+    }
+
+    /// Queue an outgoing MIDI message to be sent to the host at the end of
+    /// the current `process` call.
+    pub fn queue_midi(&mut self, data: [u8; 3], delta_frames: i32) {
+        let mut event = blank_midi_event();
+        event.delta_frames = delta_frames;
+        event.midi_data = data;
+        self.outgoing.push(event);
+    }
+
+    /// Send any MIDI queued by `queue_midi` to the host and reset the
+    /// buffer for the next `process` call. `HostCallback::process_events`
+    /// is a safe no-op when there's no real host behind it (its dispatch
+    /// only fires if a callback was actually wired up), which is exactly
+    /// the state `Whisper::default()` is in when run by the standalone
+    /// binary instead of a DAW.
+    fn flush_midi(&mut self) {
+        if self.outgoing.num_events > 0 {
+            self.host.process_events(self.outgoing.as_events());
+            self.outgoing.clear();
+        }
+    }
+}
+
+/// Available oscillator shapes, selected by `WhisperParameters::waveform`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Waveform {
+    Sine,
+    Saw,
 }
 
-struct WhisperParameters {
+impl Waveform {
+    fn from_param(value: f32) -> Self {
+        if value < 0.5 {
+            Waveform::Sine
+        } else {
+            Waveform::Saw
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Waveform::Sine => "sine",
+            Waveform::Saw => "saw",
+        }
+    }
+
+    /// Sample the waveform at `phase` (expected to be in `0.0..1.0`).
+    fn sample(self, phase: f32) -> f32 {
+        match self {
+            Waveform::Sine => (2.0 * std::f32::consts::PI * phase).sin(),
+            // Naive (non-band-limited) saw, rising from -1.0 to 1.0.
+            Waveform::Saw => 2.0 * phase - 1.0,
+        }
+    }
+}
+
+pub struct WhisperParameters {
     volume: AtomicFloat,
+    waveform: AtomicFloat,
+}
+
+impl WhisperParameters {
+    fn waveform(&self) -> Waveform {
+        Waveform::from_param(self.waveform.get())
+    }
 }
 
 impl Default for WhisperParameters {
     fn default() -> Self {
         Self {
             volume: AtomicFloat::new(1.0),
+            waveform: AtomicFloat::new(0.0),
         }
     }
 }
 
 // We're implementing a trait `Plugin` that does all the VST-y stuff for us.
 impl Plugin for Whisper {
+    fn new(host: HostCallback) -> Self {
+        Self {
+            host,
+            ..Default::default()
+        }
+    }
+
     fn get_info(&self) -> Info {
         Info {
             name: "Whisper".to_string(),
@@ -51,7 +280,7 @@ impl Plugin for Whisper {
             // Set our category
             category: Category::Synth,
 
-            parameters: 1,
+            parameters: 2,
 
             // We don't care about other stuff, and it can stay default.
             ..Default::default()
@@ -70,7 +299,15 @@ impl Plugin for Whisper {
         }
     }
 
+    fn set_sample_rate(&mut self, rate: f32) {
+        self.sample_rate = rate;
+    }
+
     fn process(&mut self, buffer: &mut AudioBuffer<f32>) {
+        // `samples()` lives on `AudioBuffer` itself, not on the `Outputs`
+        // handle `split()` returns, so read it before splitting.
+        let num_samples = buffer.samples();
+
         // `buffer.split()` gives us a tuple containing the
         // input and output buffers.  We only care about the
         // output, so we can ignore the input by using `_`.
@@ -78,29 +315,44 @@ impl Plugin for Whisper {
 
         // We only want to process *anything* if a note is being held.
         // Else, we can fill the output buffer with silence.
-        if self.notes == 0 {
+        if self.voices.is_empty() {
             for output_channel in output_buffer.into_iter() {
                 // Let's iterate over every sample in our channel.
                 for output_sample in output_channel {
                     *output_sample = 0.0;
                 }
             }
+            self.flush_midi();
             return;
         }
 
         let volume = self.params.volume.get();
+        let waveform = self.params.waveform();
+        let num_voices = self.voices.len() as f32;
+
+        // Mix every voice down to a single mono buffer first, then copy it
+        // out to all channels scaled by volume. Reuses `self.mix_buffer`
+        // instead of allocating a fresh `Vec` every block.
+        self.mix_buffer.clear();
+        self.mix_buffer.resize(num_samples, 0.0);
+        for voice in self.voices.iter_mut() {
+            for sample in self.mix_buffer.iter_mut() {
+                *sample += waveform.sample(voice.phase) * voice.gain;
+                voice.phase = (voice.phase + voice.phase_inc).fract();
+            }
+        }
 
         // Now, we want to loop over our output channels.  This
         // includes our left and right channels (or more, if you
         // are working with surround sound).
         for output_channel in output_buffer.into_iter() {
-            // Let's iterate over every sample in our channel.
-            for output_sample in output_channel {
-                // For every sample, we want to generate a random value
-                // from -1.0 to 1.0.
-                *output_sample = (random::<f32>() - 0.5f32) * 2f32 * volume;
+            for (output_sample, mixed) in output_channel.into_iter().zip(self.mix_buffer.iter()) {
+                // Normalize by voice count so adding more notes doesn't clip.
+                *output_sample = mixed / num_voices * volume;
             }
         }
+
+        self.flush_midi();
     }
 
     // Here's the function that allows us to receive events
@@ -115,14 +367,7 @@ impl Plugin for Whisper {
                     // Basically, the first byte of data tells us if this signal is a note on event
                     // or a note off event.  You can read more about that here:
                     // https://www.midi.org/specifications/item/table-1-summary-of-midi-message
-                    match ev.data[0] {
-                        // if note on, increment our counter
-                        144 => self.notes += 1u8,
-
-                        // if note off, decrement our counter
-                        128 => self.notes -= 1u8,
-                        _ => (),
-                    }
+                    self.handle_midi_message(ev.data);
                     // if we cared about the pitch of the note, it's stored in `ev.data[1]`.
                 }
                 // We don't care if we get any other type of event
@@ -141,6 +386,8 @@ impl Plugin for Whisper {
         Some(Box::new(GUIWrapper {
             inner: None,
             params: self.params.clone(),
+            host: self.host,
+            size: Arc::new(Mutex::new((WIDTH, HEIGHT))),
         }))
     }
 }
@@ -159,6 +406,7 @@ impl PluginParameters for WhisperParameters {
     fn get_parameter_text(&self, index: i32) -> String {
         match index {
             0 => format!("{:.3}", self.volume.get()),
+            1 => self.waveform().name().to_string(),
             _ => format!(""),
         }
     }
@@ -166,6 +414,7 @@ impl PluginParameters for WhisperParameters {
     fn get_parameter_name(&self, index: i32) -> String {
         match index {
             0 => "volume".to_string(),
+            1 => "waveform".to_string(),
             _ => "".to_string(),
         }
     }
@@ -173,51 +422,158 @@ impl PluginParameters for WhisperParameters {
     fn get_parameter(&self, index: i32) -> f32 {
         match index {
             0 => self.volume.get(),
+            1 => self.waveform.get(),
             _ => 0.0,
         }
     }
     fn set_parameter(&self, index: i32, value: f32) {
         match index {
             0 => self.volume.set(value),
+            1 => self.waveform.set(value),
             _ => (),
         }
     }
 }
 
 use iced_winit::Command;
-use winapi::shared::windef::HWND;
-
-use std::ops::Generator;
+use raw_window_handle::RawWindowHandle;
 
 const WIDTH: u32 = 600;
 const HEIGHT: u32 = 300;
 
 struct GUIWrapper {
     params: Arc<WhisperParameters>,
+    host: HostCallback,
+    // Live editor size, written by the GUI thread whenever the host or the
+    // user (via `Message::ResizeRequested`) changes it, and read back by
+    // `Editor::size`.
+    size: Arc<Mutex<(u32, u32)>>,
     inner: Option<GUI>,
 }
 
+/// Told to the GUI thread over `shutdown_tx`.
+#[cfg(not(target_os = "macos"))]
+enum GuiCommand {
+    Shutdown,
+}
+
+/// Owns the render thread backing the editor. The thread runs its own
+/// winit event loop and redraws continuously, independent of how often
+/// the host calls `Editor::idle`; `close` tells it to stop and joins it.
+///
+/// macOS doesn't get this: winit panics if `EventLoop::new()` (or
+/// `run_return`) is called off the main thread, and the host is only
+/// guaranteed to call `Editor::open`/`idle` on its own UI thread, not to
+/// hand us a thread we're free to block forever. So on macOS `GUI` owns
+/// the event loop directly (see the `target_os = "macos"` impl below) and
+/// `Editor::idle` pumps it in place instead of a dedicated thread redrawing
+/// on its own schedule.
+#[cfg(not(target_os = "macos"))]
 struct GUI {
-    gen: Box<dyn std::marker::Unpin + std::ops::Generator<Yield = (), Return = ()>>,
+    shutdown_tx: crossbeam_channel::Sender<GuiCommand>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+/// Wraps the raw `*mut c_void` the host hands `Editor::open` into the
+/// `raw-window-handle` variant for the platform we're built for, so the
+/// rest of `GUI::new` can stay platform-agnostic.
+fn parent_window_handle(parent: *mut c_void) -> RawWindowHandle {
+    #[cfg(target_os = "windows")]
+    {
+        let mut handle = raw_window_handle::windows::WindowsHandle::empty();
+        handle.hwnd = parent;
+        RawWindowHandle::Windows(handle)
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let mut handle = raw_window_handle::macos::MacOSHandle::empty();
+        handle.ns_view = parent;
+        RawWindowHandle::MacOS(handle)
+    }
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        let mut handle = raw_window_handle::unix::XcbHandle::empty();
+        handle.window = parent as u32;
+        RawWindowHandle::Xcb(handle)
+    }
+}
+
+/// Builds the child editor window embedded into `parent`, dispatching to
+/// the platform-specific winit extension trait for the handle variant
+/// `parent_window_handle` produced. macOS has no winit-level "parent
+/// window" concept, so we build a normal window and reparent its `NSView`
+/// directly via Cocoa.
+fn build_child_window(
+    event_loop: &iced_winit::winit::event_loop::EventLoop<()>,
+    builder: iced_winit::winit::window::WindowBuilder,
+    handle: RawWindowHandle,
+) -> iced_winit::winit::window::Window {
+    match handle {
+        #[cfg(target_os = "windows")]
+        RawWindowHandle::Windows(handle) => {
+            use iced_winit::winit::platform::windows::WindowBuilderExtWindows;
+            builder
+                .with_parent_window(handle.hwnd as winapi::shared::windef::HWND)
+                .build(event_loop)
+                .unwrap()
+        }
+        #[cfg(all(unix, not(target_os = "macos")))]
+        RawWindowHandle::Xcb(handle) => {
+            use iced_winit::winit::platform::unix::WindowBuilderExtUnix;
+            // `XcbHandle::window` is a `u32` (the XID width), but
+            // `with_x11_parent` takes the X11 `Window` type, which is a
+            // `c_ulong` (64 bits on a typical Linux target) — widen it
+            // explicitly rather than relying on an implicit conversion.
+            builder
+                .with_x11_parent(handle.window as std::os::raw::c_ulong)
+                .build(event_loop)
+                .unwrap()
+        }
+        #[cfg(target_os = "macos")]
+        RawWindowHandle::MacOS(handle) => {
+            let window = builder.build(event_loop).unwrap();
+            unsafe {
+                embed_ns_view(&window, handle.ns_view);
+            }
+            window
+        }
+        _ => unreachable!("unsupported window handle for this platform"),
+    }
+}
+
+#[cfg(target_os = "macos")]
+unsafe fn embed_ns_view(window: &iced_winit::winit::window::Window, parent_ns_view: *mut c_void) {
+    use cocoa::appkit::NSView;
+    use raw_window_handle::macos::MacOSHandle;
+    use raw_window_handle::HasRawWindowHandle;
+
+    let child_handle = match window.raw_window_handle() {
+        RawWindowHandle::MacOS(MacOSHandle { ns_view, .. }) => ns_view,
+        _ => unreachable!(),
+    };
+
+    let parent_view = parent_ns_view as cocoa::base::id;
+    let child_view = child_handle as cocoa::base::id;
+    parent_view.addSubview_(child_view);
 }
 
+#[cfg(not(target_os = "macos"))]
 impl GUI {
-    fn new(parent: HWND, params: Arc<WhisperParameters>) -> Self {
-        /*
-        let mut setting = iced_winit::settings::Settings {
-            window: Default::default(),
-            flags: params.clone(),
-        };
-        // Settings for VST
-        setting.window.decorations = false;
-        setting.window.platform_specific.parent = Some(parent);
-        setting.window.size = (WIDTH, HEIGHT);
-        // setting.window.resizable = true;
-        */
-
-        // Initialize `Application` to share `params`
-        // Save Box of `Generator` to do event loop on idle method
-        let gen = Box::new(move || {
+    fn new(
+        parent: *mut c_void,
+        params: Arc<WhisperParameters>,
+        host: HostCallback,
+        size: Arc<Mutex<(u32, u32)>>,
+    ) -> Self {
+        let handle = parent_window_handle(parent);
+        let (shutdown_tx, shutdown_rx) = crossbeam_channel::unbounded();
+
+        // The GUI thread owns the `EventLoop`, window, swap chain, and
+        // iced `program::State`. Parameter reads/writes go straight
+        // through the shared `Arc<WhisperParameters>` atomics, so the
+        // only thing this thread needs from the audio/plugin side over
+        // a channel is the shutdown signal.
+        let thread = std::thread::spawn(move || {
             use iced_wgpu::{wgpu, Backend, Renderer, Settings, Viewport};
             use iced_winit::{futures, program, winit, Application, Debug, Size};
 
@@ -225,20 +581,19 @@ impl GUI {
                 event::{Event, ModifiersState, WindowEvent},
                 event_loop::{ControlFlow, EventLoop},
                 platform::desktop::EventLoopExtDesktop,
-                platform::windows::WindowBuilderExtWindows,
             };
             let mut event_loop = EventLoop::new();
 
-            let window = winit::window::WindowBuilder::new()
-                .with_decorations(false)
-                .with_parent_window(parent)
-                .with_inner_size(winit::dpi::PhysicalSize {
-                    width: WIDTH,
-                    height: HEIGHT,
-                })
-                .build(&event_loop)
-                .unwrap();
-            // let window = winit::window::Window::new(&event_loop).unwrap();
+            let window = build_child_window(
+                &event_loop,
+                winit::window::WindowBuilder::new()
+                    .with_decorations(false)
+                    .with_inner_size(winit::dpi::PhysicalSize {
+                        width: WIDTH,
+                        height: HEIGHT,
+                    }),
+                handle,
+            );
 
             let physical_size = window.inner_size();
             log::info!("physical_size {:?}", physical_size);
@@ -292,7 +647,7 @@ impl GUI {
             let mut resized = false;
 
             // Initialize scene and GUI controls
-            let controls = WhisperGUI::new(params);
+            let controls = WhisperGUI::new(params, Arc::clone(&size));
 
             // Initialize iced
             let mut debug = Debug::new();
@@ -300,13 +655,18 @@ impl GUI {
 
             let mut state =
                 program::State::new(controls, viewport.logical_size(), &mut renderer, &mut debug);
-            yield;
             let mut closed = false;
 
             while !closed {
                 event_loop.run_return(|event, _, control_flow| {
-                    // You should change this if you want to render continuosly
-                    *control_flow = ControlFlow::Exit;
+                    // Keep pumping continuously: redraw cadence is owned
+                    // by this thread now, not by how often the host calls
+                    // `Editor::idle`.
+                    *control_flow = ControlFlow::Poll;
+
+                    if let Ok(GuiCommand::Shutdown) = shutdown_rx.try_recv() {
+                        closed = true;
+                    }
 
                     match event {
                         Event::WindowEvent { event, .. } => {
@@ -322,12 +682,12 @@ impl GUI {
                                         Size::new(new_size.width, new_size.height),
                                         window.scale_factor(),
                                     );
+                                    *size.lock().unwrap() = (new_size.width, new_size.height);
 
                                     resized = true;
                                 }
                                 WindowEvent::CloseRequested => {
                                     closed = true;
-                                    *control_flow = ControlFlow::Exit;
                                 }
 
                                 _ => {}
@@ -351,6 +711,26 @@ impl GUI {
                                 &mut debug,
                             );
 
+                            // If `WhisperGUI` requested a new size (via
+                            // `Message::ResizeRequested`, from either the
+                            // "Enlarge" or "Shrink" button), resize the
+                            // window and ask the host to follow along via
+                            // `size_window` (the `audioMasterSizeWindow`
+                            // opcode). Once winit reports the window
+                            // actually changed, the `WindowEvent::Resized`
+                            // handler above rebuilds the viewport and sets
+                            // `resized`, and the swap chain is recreated on
+                            // the next `RedrawRequested`.
+                            let requested_size = *size.lock().unwrap();
+                            let current_size = window.inner_size();
+                            if requested_size != (current_size.width, current_size.height) {
+                                window.set_inner_size(winit::dpi::PhysicalSize {
+                                    width: requested_size.0,
+                                    height: requested_size.1,
+                                });
+                                host.size_window(requested_size.0 as i32, requested_size.1 as i32);
+                            }
+
                             // and request a redraw
                             window.request_redraw();
                         }
@@ -417,19 +797,316 @@ impl GUI {
                         }
                         _ => {}
                     }
+
+                    if closed {
+                        *control_flow = ControlFlow::Exit;
+                    }
                 });
-                yield;
             }
         });
 
-        Self { gen }
+        Self {
+            shutdown_tx,
+            thread: Some(thread),
+        }
+    }
+}
+
+/// macOS counterpart to the thread-owned `GUI` above. Builds the same
+/// window/wgpu/iced state, but synchronously on the calling thread —
+/// `Editor::open` is required to run on the host's UI thread, which is the
+/// only thread winit's `EventLoop::new()` is allowed to run on here — and
+/// pumps one winit `Poll` cycle per `Editor::idle` call instead of redrawing
+/// on its own schedule.
+#[cfg(target_os = "macos")]
+struct GUI {
+    event_loop: iced_winit::winit::event_loop::EventLoop<()>,
+    window: iced_winit::winit::window::Window,
+    surface: iced_wgpu::wgpu::Surface,
+    device: iced_wgpu::wgpu::Device,
+    queue: iced_wgpu::wgpu::Queue,
+    swap_chain: iced_wgpu::wgpu::SwapChain,
+    format: iced_wgpu::wgpu::TextureFormat,
+    viewport: iced_wgpu::Viewport,
+    modifiers: iced_winit::winit::event::ModifiersState,
+    resized: bool,
+    renderer: iced_wgpu::Renderer,
+    debug: iced_winit::Debug,
+    state: iced_winit::program::State<WhisperGUI>,
+    size: Arc<Mutex<(u32, u32)>>,
+    host: HostCallback,
+}
+
+#[cfg(target_os = "macos")]
+impl GUI {
+    fn new(
+        parent: *mut c_void,
+        params: Arc<WhisperParameters>,
+        host: HostCallback,
+        size: Arc<Mutex<(u32, u32)>>,
+    ) -> Self {
+        use iced_wgpu::{wgpu, Backend, Renderer, Settings, Viewport};
+        use iced_winit::{futures, program, winit, Debug, Size};
+
+        use winit::{event::ModifiersState, event_loop::EventLoop};
+
+        let handle = parent_window_handle(parent);
+        let event_loop = EventLoop::new();
+
+        let window = build_child_window(
+            &event_loop,
+            winit::window::WindowBuilder::new()
+                .with_decorations(false)
+                .with_inner_size(winit::dpi::PhysicalSize {
+                    width: WIDTH,
+                    height: HEIGHT,
+                }),
+            handle,
+        );
+
+        let physical_size = window.inner_size();
+        log::info!("physical_size {:?}", physical_size);
+        log::info!("scale_factor {:?}", window.scale_factor());
+        let viewport = Viewport::with_physical_size(
+            Size::new(physical_size.width, physical_size.height),
+            window.scale_factor(),
+        );
+        let modifiers = ModifiersState::default();
+
+        // Initialize wgpu
+        let surface = wgpu::Surface::create(&window);
+        let (mut device, queue) = futures::executor::block_on(async {
+            let adapter = wgpu::Adapter::request(
+                &wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::Default,
+                    compatible_surface: Some(&surface),
+                },
+                wgpu::BackendBit::PRIMARY,
+            )
+            .await
+            .expect("Request adapter");
+
+            adapter
+                .request_device(&wgpu::DeviceDescriptor {
+                    extensions: wgpu::Extensions {
+                        anisotropic_filtering: false,
+                    },
+                    limits: wgpu::Limits::default(),
+                })
+                .await
+        });
+
+        let format = wgpu::TextureFormat::Bgra8UnormSrgb;
+
+        let swap_chain = {
+            let size = window.inner_size();
+
+            device.create_swap_chain(
+                &surface,
+                &wgpu::SwapChainDescriptor {
+                    usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+                    format: format,
+                    width: size.width,
+                    height: size.height,
+                    present_mode: wgpu::PresentMode::Mailbox,
+                },
+            )
+        };
+
+        // Initialize scene and GUI controls
+        let controls = WhisperGUI::new(params, Arc::clone(&size));
+
+        // Initialize iced
+        let mut debug = Debug::new();
+        let mut renderer = Renderer::new(Backend::new(&mut device, Settings::default()));
+
+        let state =
+            program::State::new(controls, viewport.logical_size(), &mut renderer, &mut debug);
+
+        Self {
+            event_loop,
+            window,
+            surface,
+            device,
+            queue,
+            swap_chain,
+            format,
+            viewport,
+            modifiers,
+            resized: false,
+            renderer,
+            debug,
+            state,
+            size,
+            host,
+        }
+    }
+
+    /// Pumps one `Poll` cycle of the winit event loop: drains pending
+    /// window events, lets iced update, resizes the swap chain if needed,
+    /// and redraws. Called from `Editor::idle`, which the host is
+    /// guaranteed to call on the same UI thread `Editor::open` ran on.
+    fn pump(&mut self) {
+        use iced_wgpu::wgpu;
+        use iced_winit::winit::{
+            self,
+            event::{Event, WindowEvent},
+            event_loop::ControlFlow,
+            platform::desktop::EventLoopExtDesktop,
+        };
+        use iced_winit::Size;
+
+        let Self {
+            event_loop,
+            window,
+            surface,
+            device,
+            queue,
+            swap_chain,
+            format,
+            viewport,
+            modifiers,
+            resized,
+            renderer,
+            debug,
+            state,
+            size,
+            host,
+        } = self;
+
+        event_loop.run_return(|event, _, control_flow| {
+            *control_flow = ControlFlow::Poll;
+
+            match event {
+                Event::WindowEvent { event, .. } => {
+                    match event {
+                        WindowEvent::Resized(new_size) => {
+                            log::info!("change viewport {:?}", new_size);
+                            *viewport = iced_wgpu::Viewport::with_physical_size(
+                                Size::new(new_size.width, new_size.height),
+                                window.scale_factor(),
+                            );
+                            *size.lock().unwrap() = (new_size.width, new_size.height);
+
+                            *resized = true;
+                        }
+                        // This editor has no window chrome to close, and
+                        // lifecycle is driven by the host calling
+                        // `Editor::close`, not by a window-level close
+                        // button.
+                        WindowEvent::CloseRequested => {}
+                        _ => {}
+                    }
+
+                    // Map window event to iced event
+                    if let Some(event) = iced_winit::conversion::window_event(
+                        &event,
+                        window.scale_factor(),
+                        *modifiers,
+                    ) {
+                        state.queue_event(event);
+                    }
+                }
+                Event::MainEventsCleared => {
+                    // We update iced
+                    let _ = state.update(None, viewport.logical_size(), renderer, debug);
+
+                    // If `WhisperGUI` requested a new size (via
+                    // `Message::ResizeRequested`, from either the
+                    // "Enlarge" or "Shrink" button), resize the window and
+                    // ask the host to follow along via `size_window` (the
+                    // `audioMasterSizeWindow` opcode). Once winit reports
+                    // the window actually changed, the `WindowEvent::Resized`
+                    // handler above rebuilds the viewport and sets
+                    // `resized`, and the swap chain is recreated on the next
+                    // `RedrawRequested`.
+                    let requested_size = *size.lock().unwrap();
+                    let current_size = window.inner_size();
+                    if requested_size != (current_size.width, current_size.height) {
+                        window.set_inner_size(winit::dpi::PhysicalSize {
+                            width: requested_size.0,
+                            height: requested_size.1,
+                        });
+                        host.size_window(requested_size.0 as i32, requested_size.1 as i32);
+                    }
+
+                    window.request_redraw();
+                }
+                Event::RedrawRequested(_) => {
+                    if *resized {
+                        let size = window.inner_size();
+
+                        *swap_chain = device.create_swap_chain(
+                            surface,
+                            &wgpu::SwapChainDescriptor {
+                                usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+                                format: *format,
+                                width: size.width,
+                                height: size.height,
+                                present_mode: wgpu::PresentMode::Mailbox,
+                            },
+                        );
+                        *resized = false;
+                    }
+
+                    let frame = swap_chain.get_next_texture().expect("Next frame");
+
+                    let mut encoder =
+                        device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                            label: None,
+                        });
+
+                    let _ = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                            attachment: &frame.view,
+                            resolve_target: None,
+                            load_op: wgpu::LoadOp::Clear,
+                            store_op: wgpu::StoreOp::Store,
+                            clear_color: wgpu::Color {
+                                r: 1.0,
+                                g: 1.0,
+                                b: 1.0,
+                                a: 1.0,
+                            },
+                        }],
+                        depth_stencil_attachment: None,
+                    });
+
+                    // We draw the scene first
+                    let program = state.program();
+
+                    // scene.draw(&mut encoder, &frame.view, program.background_color());
+
+                    // And then iced on top
+                    let mouse_interaction = renderer.backend_mut().draw(
+                        device,
+                        &mut encoder,
+                        &frame.view,
+                        viewport,
+                        state.primitive(),
+                        &debug.overlay(),
+                    );
+
+                    queue.submit(&[encoder.finish()]);
+
+                    window.set_cursor_icon(iced_winit::conversion::mouse_interaction(
+                        mouse_interaction,
+                    ));
+                }
+                Event::RedrawEventsCleared => {
+                    *control_flow = ControlFlow::Exit;
+                }
+                _ => {}
+            }
+        });
     }
 }
 
 impl Editor for GUIWrapper {
     fn size(&self) -> (i32, i32) {
         log::info!("GUI size");
-        (WIDTH as i32, HEIGHT as i32)
+        let (width, height) = *self.size.lock().unwrap();
+        (width as i32, height as i32)
     }
 
     fn position(&self) -> (i32, i32) {
@@ -437,29 +1114,44 @@ impl Editor for GUIWrapper {
         (0, 0)
     }
 
+    #[cfg(not(target_os = "macos"))]
     fn idle(&mut self) {
-        log::info!("GUI idle");
-        // Poll events here
+        // Nothing to pump here: the render thread redraws on its own
+        // schedule, not in lockstep with host `idle` calls.
+    }
+
+    #[cfg(target_os = "macos")]
+    fn idle(&mut self) {
+        // On macOS `GUI` owns its event loop synchronously rather than on a
+        // dedicated thread (winit requires the main/UI thread here), so
+        // `idle` is what actually pumps it.
         if let Some(inner) = self.inner.as_mut() {
-            log::info!("GUI idle run");
-            if let std::ops::GeneratorState::Complete(_) =
-                Generator::resume(std::pin::Pin::new(&mut inner.gen), ())
-            {
-                self.inner = None;
+            inner.pump();
+        }
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    fn close(&mut self) {
+        log::info!("GUI close");
+        if let Some(inner) = self.inner.take() {
+            let _ = inner.shutdown_tx.send(GuiCommand::Shutdown);
+            if let Some(thread) = inner.thread {
+                let _ = thread.join();
             }
         }
+        log::info!("GUI closed");
     }
 
+    #[cfg(target_os = "macos")]
     fn close(&mut self) {
         log::info!("GUI close");
-        self.inner = None;
+        self.inner.take();
         log::info!("GUI closed");
     }
 
     fn open(&mut self, parent: *mut c_void) -> bool {
         log::info!("GUI open");
-        let gui = GUI::new(parent as HWND, self.params.clone());
-        // Generator::resume(std::pin::Pin::new(&mut gui.gen), ());
+        let gui = GUI::new(parent, self.params.clone(), self.host, Arc::clone(&self.size));
         self.inner = Some(gui);
 
         log::info!("GUI opened");
@@ -477,14 +1169,24 @@ use iced::{Column, Element, Subscription, Text};
 // `Application`
 struct WhisperGUI {
     params: Arc<WhisperParameters>,
+    // Shared with the GUI thread's event loop, which notices a change here
+    // and drives the actual window/host resize.
+    size: Arc<Mutex<(u32, u32)>>,
     volume_slider: iced::widget::slider::State,
+    enlarge_button: iced::widget::button::State,
+    shrink_button: iced::widget::button::State,
+    waveform_button: iced::widget::button::State,
 }
 
 impl WhisperGUI {
-    fn new(params: Arc<WhisperParameters>) -> Self {
+    fn new(params: Arc<WhisperParameters>, size: Arc<Mutex<(u32, u32)>>) -> Self {
         Self {
             params,
+            size,
             volume_slider: Default::default(),
+            enlarge_button: Default::default(),
+            shrink_button: Default::default(),
+            waveform_button: Default::default(),
         }
     }
 }
@@ -492,6 +1194,8 @@ impl WhisperGUI {
 #[derive(Debug, Clone, Copy)]
 enum Message {
     VolumeChanged(f32),
+    ResizeRequested(u32, u32),
+    WaveformToggled,
 }
 
 impl iced_winit::Program for WhisperGUI {
@@ -504,6 +1208,20 @@ impl iced_winit::Program for WhisperGUI {
             Message::VolumeChanged(v) => {
                 self.params.volume.set(v);
             }
+            Message::ResizeRequested(width, height) => {
+                *self.size.lock().unwrap() = (width, height);
+            }
+            Message::WaveformToggled => {
+                let next = match self.params.waveform() {
+                    Waveform::Sine => Waveform::Saw,
+                    Waveform::Saw => Waveform::Sine,
+                };
+                self.params.waveform.set(if next == Waveform::Sine {
+                    0.0
+                } else {
+                    1.0
+                });
+            }
         }
         Command::none()
     }
@@ -519,16 +1237,29 @@ impl iced_winit::Program for WhisperGUI {
                 self.params.volume.get(),
                 Message::VolumeChanged,
             ))
+            .push(Text::new(format!("Waveform: {}", self.params.waveform().name())).size(24))
+            .push(
+                iced::widget::Button::new(&mut self.waveform_button, Text::new("Toggle waveform"))
+                    .on_press(Message::WaveformToggled),
+            )
+            .push(
+                iced::widget::Button::new(&mut self.enlarge_button, Text::new("Enlarge"))
+                    .on_press(Message::ResizeRequested(WIDTH * 2, HEIGHT * 2)),
+            )
+            .push(
+                iced::widget::Button::new(&mut self.shrink_button, Text::new("Shrink"))
+                    .on_press(Message::ResizeRequested(WIDTH, HEIGHT)),
+            )
             .into()
     }
 }
 
 impl iced_winit::Application for WhisperGUI {
-    type Flags = Arc<WhisperParameters>;
+    type Flags = (Arc<WhisperParameters>, Arc<Mutex<(u32, u32)>>);
 
     fn new(flags: Self::Flags) -> (Self, Command<Self::Message>) {
         log::info!("iced new");
-        (Self::new(flags), Command::none())
+        (Self::new(flags.0, flags.1), Command::none())
     }
 
     fn title(&self) -> String {
@@ -541,3 +1272,265 @@ impl iced_winit::Application for WhisperGUI {
         Subscription::none()
     }
 }
+
+/// Opens `WhisperGUI` in its own top-level window instead of embedding it
+/// inside a DAW-owned parent. Used by the standalone (`cargo run`) host,
+/// which has no `HWND`/`NSView` to parent into and simply owns the whole
+/// process, so it can drive the winit event loop directly rather than
+/// going through `GUIWrapper`'s embedded child-window editor.
+pub fn run_standalone_gui(params: Arc<WhisperParameters>) -> ! {
+    use iced_wgpu::{wgpu, Backend, Renderer, Settings, Viewport};
+    use iced_winit::{futures, program, winit, Application, Debug, Size};
+
+    use winit::{
+        event::{Event, ModifiersState, WindowEvent},
+        event_loop::{ControlFlow, EventLoop},
+    };
+
+    let event_loop = EventLoop::new();
+
+    let window = winit::window::WindowBuilder::new()
+        .with_title("Whisper")
+        .with_inner_size(winit::dpi::PhysicalSize {
+            width: WIDTH,
+            height: HEIGHT,
+        })
+        .build(&event_loop)
+        .unwrap();
+
+    let physical_size = window.inner_size();
+    let mut viewport = Viewport::with_physical_size(
+        Size::new(physical_size.width, physical_size.height),
+        window.scale_factor(),
+    );
+    let mut modifiers = ModifiersState::default();
+
+    let surface = wgpu::Surface::create(&window);
+    let (mut device, queue) = futures::executor::block_on(async {
+        let adapter = wgpu::Adapter::request(
+            &wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::Default,
+                compatible_surface: Some(&surface),
+            },
+            wgpu::BackendBit::PRIMARY,
+        )
+        .await
+        .expect("Request adapter");
+
+        adapter
+            .request_device(&wgpu::DeviceDescriptor {
+                extensions: wgpu::Extensions {
+                    anisotropic_filtering: false,
+                },
+                limits: wgpu::Limits::default(),
+            })
+            .await
+    });
+
+    let format = wgpu::TextureFormat::Bgra8UnormSrgb;
+
+    let mut swap_chain = {
+        let size = window.inner_size();
+
+        device.create_swap_chain(
+            &surface,
+            &wgpu::SwapChainDescriptor {
+                usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+                format,
+                width: size.width,
+                height: size.height,
+                present_mode: wgpu::PresentMode::Mailbox,
+            },
+        )
+    };
+    let mut resized = false;
+    let size = Arc::new(Mutex::new((WIDTH, HEIGHT)));
+
+    let controls = WhisperGUI::new(params, Arc::clone(&size));
+
+    let mut debug = Debug::new();
+    let mut renderer = Renderer::new(Backend::new(&mut device, Settings::default()));
+
+    let mut state =
+        program::State::new(controls, viewport.logical_size(), &mut renderer, &mut debug);
+
+    event_loop.run(move |event, _, control_flow| {
+        *control_flow = ControlFlow::Poll;
+
+        match event {
+            Event::WindowEvent { event, .. } => {
+                match event {
+                    WindowEvent::Resized(new_size) => {
+                        viewport = Viewport::with_physical_size(
+                            Size::new(new_size.width, new_size.height),
+                            window.scale_factor(),
+                        );
+                        *size.lock().unwrap() = (new_size.width, new_size.height);
+
+                        resized = true;
+                    }
+                    WindowEvent::CloseRequested => {
+                        *control_flow = ControlFlow::Exit;
+                    }
+                    _ => {}
+                }
+
+                if let Some(event) =
+                    iced_winit::conversion::window_event(&event, window.scale_factor(), modifiers)
+                {
+                    state.queue_event(event);
+                }
+            }
+            Event::MainEventsCleared => {
+                let _ = state.update(None, viewport.logical_size(), &mut renderer, &mut debug);
+
+                // No host to negotiate with here, so just grow the window
+                // directly when `WhisperGUI` requests a new size.
+                let requested_size = *size.lock().unwrap();
+                let current_size = window.inner_size();
+                if requested_size != (current_size.width, current_size.height) {
+                    window.set_inner_size(winit::dpi::PhysicalSize {
+                        width: requested_size.0,
+                        height: requested_size.1,
+                    });
+                }
+
+                window.request_redraw();
+            }
+            Event::RedrawRequested(_) => {
+                if resized {
+                    let size = window.inner_size();
+
+                    swap_chain = device.create_swap_chain(
+                        &surface,
+                        &wgpu::SwapChainDescriptor {
+                            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+                            format,
+                            width: size.width,
+                            height: size.height,
+                            present_mode: wgpu::PresentMode::Mailbox,
+                        },
+                    );
+                    resized = false;
+                }
+
+                let frame = swap_chain.get_next_texture().expect("Next frame");
+
+                let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: None,
+                });
+
+                let _ = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                        attachment: &frame.view,
+                        resolve_target: None,
+                        load_op: wgpu::LoadOp::Clear,
+                        store_op: wgpu::StoreOp::Store,
+                        clear_color: wgpu::Color {
+                            r: 1.0,
+                            g: 1.0,
+                            b: 1.0,
+                            a: 1.0,
+                        },
+                    }],
+                    depth_stencil_attachment: None,
+                });
+
+                let mouse_interaction = renderer.backend_mut().draw(
+                    &mut device,
+                    &mut encoder,
+                    &frame.view,
+                    &viewport,
+                    state.primitive(),
+                    &debug.overlay(),
+                );
+
+                queue.submit(&[encoder.finish()]);
+
+                window.set_cursor_icon(iced_winit::conversion::mouse_interaction(
+                    mouse_interaction,
+                ));
+            }
+            _ => {}
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn note_on_adds_a_voice_and_note_off_removes_it() {
+        let mut whisper = Whisper::default();
+        whisper.handle_midi_message([144, 60, 100]);
+        assert_eq!(whisper.voices.len(), 1);
+        assert_eq!(whisper.voices[0].note, 60);
+
+        whisper.handle_midi_message([128, 60, 0]);
+        assert!(whisper.voices.is_empty());
+    }
+
+    #[test]
+    fn note_on_with_zero_velocity_is_treated_as_note_off() {
+        let mut whisper = Whisper::default();
+        whisper.handle_midi_message([144, 60, 100]);
+        whisper.handle_midi_message([144, 60, 0]);
+        assert!(whisper.voices.is_empty());
+    }
+
+    #[test]
+    fn repeated_note_on_retriggers_instead_of_stacking() {
+        let mut whisper = Whisper::default();
+        whisper.handle_midi_message([144, 60, 100]);
+        whisper.handle_midi_message([144, 60, 50]);
+        assert_eq!(whisper.voices.len(), 1);
+        assert_eq!(whisper.voices[0].gain, 50.0 / 127.0);
+    }
+
+    #[test]
+    fn note_off_only_removes_the_matching_voice() {
+        let mut whisper = Whisper::default();
+        whisper.handle_midi_message([144, 60, 100]);
+        whisper.handle_midi_message([144, 64, 100]);
+        whisper.handle_midi_message([128, 60, 0]);
+
+        assert_eq!(whisper.voices.len(), 1);
+        assert_eq!(whisper.voices[0].note, 64);
+    }
+
+    #[test]
+    fn note_on_frequency_matches_equal_temperament() {
+        let mut whisper = Whisper::default();
+        whisper.sample_rate = 44_100.0;
+        // Note 69 is A4 (440 Hz).
+        whisper.handle_midi_message([144, 69, 127]);
+        let phase_inc = whisper.voices[0].phase_inc;
+        assert!((phase_inc - 440.0 / 44_100.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn waveform_from_param_thresholds_at_one_half() {
+        assert_eq!(Waveform::from_param(0.0), Waveform::Sine);
+        assert_eq!(Waveform::from_param(0.49), Waveform::Sine);
+        assert_eq!(Waveform::from_param(0.5), Waveform::Saw);
+        assert_eq!(Waveform::from_param(1.0), Waveform::Saw);
+    }
+
+    #[test]
+    fn waveform_sample_matches_known_points() {
+        assert!((Waveform::Sine.sample(0.0) - 0.0).abs() < 1e-6);
+        assert!((Waveform::Sine.sample(0.25) - 1.0).abs() < 1e-6);
+        assert!((Waveform::Saw.sample(0.0) - (-1.0)).abs() < 1e-6);
+        assert!((Waveform::Saw.sample(1.0) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn send_event_buffer_drops_events_past_capacity() {
+        let mut buffer = SendEventBuffer::new();
+        for _ in 0..MAX_OUTGOING_EVENTS + 10 {
+            buffer.push(blank_midi_event());
+        }
+        assert_eq!(buffer.num_events as usize, MAX_OUTGOING_EVENTS);
+    }
+}